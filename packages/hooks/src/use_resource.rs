@@ -1,14 +1,17 @@
 #![allow(missing_docs)]
 
-use crate::{use_callback, use_signal, UseCallback};
+use crate::{use_callback, use_drop, use_signal, UseCallback};
 use dioxus_core::prelude::*;
 use dioxus_core::{
     prelude::{spawn, use_hook},
     Task,
 };
 use dioxus_signals::*;
-use futures_util::{future, pin_mut, FutureExt, StreamExt};
+use dioxus_time::sleep;
+use futures_util::{future, pin_mut, stream, FutureExt, Stream, StreamExt};
 use std::ops::Deref;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
 use std::{cell::Cell, future::Future, rc::Rc};
 
 /// A memo that resolve to a value asynchronously.
@@ -40,37 +43,263 @@ use std::{cell::Cell, future::Future, rc::Rc};
 /// ```
 #[must_use = "Consider using `cx.spawn` to run a future without reading its value"]
 pub fn use_resource<T, F>(future: impl Fn() -> F + 'static) -> Resource<T>
+where
+    T: 'static,
+    F: Future<Output = T> + 'static,
+{
+    use_resource_with_options(future, ResourceOptions::default())
+}
+
+/// Options for [`use_resource_with_options`].
+///
+/// `T` only matters if you call [`Self::retry`] - it ties the options to the same `T` the
+/// hook resolves to, so leave it to be inferred.
+pub struct ResourceOptions<T = ()> {
+    debounce: Option<Duration>,
+    retry: Option<RetryPolicy<T>>,
+}
+
+impl<T> Default for ResourceOptions<T> {
+    fn default() -> Self {
+        Self {
+            debounce: None,
+            retry: None,
+        }
+    }
+}
+
+impl<T> Clone for ResourceOptions<T> {
+    fn clone(&self) -> Self {
+        Self {
+            debounce: self.debounce,
+            retry: self.retry.clone(),
+        }
+    }
+}
+
+impl<T> ResourceOptions<T> {
+    /// Create a new, default set of options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for this long after the last dependency change before restarting the future,
+    /// instead of restarting on every single change. Useful for inputs like a search box
+    /// that change rapidly but should only trigger one request once the user stops typing.
+    pub fn debounce(mut self, duration: Duration) -> Self {
+        self.debounce = Some(duration);
+        self
+    }
+
+    /// Automatically re-run the future up to `policy.max_attempts` times, with a growing
+    /// delay between attempts, whenever it resolves to something `policy` considers a
+    /// failure (see [`RetryPolicy`]).
+    pub fn retry(mut self, policy: RetryPolicy<T>) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+}
+
+/// How the delay between retry attempts grows, used by [`RetryPolicy`].
+#[derive(Clone, Copy, Debug)]
+pub enum Backoff {
+    /// Keep the delay between attempts the same every time.
+    Constant,
+
+    /// Multiply the delay by `factor` after each failed attempt.
+    Exponential {
+        /// The multiplier applied to the delay after each failed attempt.
+        factor: f64,
+    },
+}
+
+/// A retry policy for [`ResourceOptions::retry`].
+///
+/// `is_err` decides whether a resolved `T` counts as a failure worth retrying. It has to be
+/// supplied explicitly (rather than inferred from `T`) because `use_resource_with_options`
+/// is generic over `T` - there's no way to ask an abstract type parameter whether it happens
+/// to be a `Result` from inside that generic function. Use [`Self::for_result`] for the
+/// common case where `T` is a `Result<O, E>` and any `Err` should be retried.
+pub struct RetryPolicy<T> {
+    /// How many times to retry after the initial attempt fails.
+    pub max_attempts: u32,
+
+    /// The delay before the first retry.
+    pub base_delay: Duration,
+
+    /// How the delay grows after each subsequent failed attempt.
+    pub backoff: Backoff,
+
+    /// An optional ceiling on the delay between attempts.
+    pub max_delay: Option<Duration>,
+
+    is_err: Rc<dyn Fn(&T) -> bool>,
+}
+
+impl<T> Clone for RetryPolicy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            max_attempts: self.max_attempts,
+            base_delay: self.base_delay,
+            backoff: self.backoff,
+            max_delay: self.max_delay,
+            is_err: self.is_err.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for RetryPolicy<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("backoff", &self.backoff)
+            .field("max_delay", &self.max_delay)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> RetryPolicy<T> {
+    /// Retry up to `max_attempts` times, waiting `base_delay` between each attempt.
+    /// `is_err` is called with each resolved value to decide whether it counts as a failure.
+    pub fn new(
+        max_attempts: u32,
+        base_delay: Duration,
+        is_err: impl Fn(&T) -> bool + 'static,
+    ) -> Self {
+        Self {
+            max_attempts,
+            base_delay,
+            backoff: Backoff::Constant,
+            max_delay: None,
+            is_err: Rc::new(is_err),
+        }
+    }
+
+    /// Grow the delay between attempts exponentially by `factor`.
+    pub fn exponential(mut self, factor: f64) -> Self {
+        self.backoff = Backoff::Exponential { factor };
+        self
+    }
+
+    /// Cap the delay between attempts at `max_delay`.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// The delay to wait before the `attempt`th retry (1-indexed).
+    fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let delay = match self.backoff {
+            Backoff::Constant => self.base_delay,
+            Backoff::Exponential { factor } => self
+                .base_delay
+                .mul_f64(factor.powi(attempt.saturating_sub(1) as i32)),
+        };
+
+        match self.max_delay {
+            Some(max_delay) => delay.min(max_delay),
+            None => delay,
+        }
+    }
+}
+
+impl<O, E> RetryPolicy<Result<O, E>> {
+    /// Retry up to `max_attempts` times, waiting `base_delay` between each attempt, whenever
+    /// the future resolves to `Err`.
+    pub fn for_result(max_attempts: u32, base_delay: Duration) -> Self {
+        Self::new(max_attempts, base_delay, Result::is_err)
+    }
+}
+
+/// The same as [`use_resource`], but accepts [`ResourceOptions`] to configure things like
+/// debouncing of dependency changes.
+#[must_use = "Consider using `cx.spawn` to run a future without reading its value"]
+pub fn use_resource_with_options<T, F>(
+    future: impl Fn() -> F + 'static,
+    options: ResourceOptions<T>,
+) -> Resource<T>
 where
     T: 'static,
     F: Future<Output = T> + 'static,
 {
     let mut value = use_signal(|| None);
     let mut state = use_signal(|| UseResourceState::Pending);
+    let mut retry_attempt = use_signal(|| 0u32);
     let (rc, changed) = use_hook(|| {
         let (rc, changed) = ReactiveContext::new();
         (rc, Rc::new(Cell::new(Some(changed))))
     });
+    let mut on_cancel = use_hook(|| Signal::new(None::<Box<dyn FnOnce()>>));
 
-    let cb = use_callback(move || {
-        // Create the user's task
-        #[allow(clippy::redundant_closure)]
-        let fut = rc.run_in(|| future());
-
-        // Spawn a wrapper task that polls the innner future and watch its dependencies
-        spawn(async move {
-            // move the future here and pin it so we can poll it
-            let fut = fut;
-            pin_mut!(fut);
-
-            // Run each poll in the context of the reactive scope
-            // This ensures the scope is properly subscribed to the future's dependencies
-            let res = future::poll_fn(|cx| rc.run_in(|| fut.poll_unpin(cx))).await;
+    // Make sure any registered `on_cancel` callback still runs if the component is torn down
+    // outright, not just when the resource is explicitly cancelled or restarted
+    use_drop(move || {
+        if let Some(f) = on_cancel.write().take() {
+            f();
+        }
+    });
 
-            // Set the value and state
-            state.set(UseResourceState::Ready);
-            value.set(Some(res));
+    let cb = {
+        let options = options.clone();
+        let future = Rc::new(future);
+        use_callback(move || {
+            // Reset the state and retry counter for this fresh run
+            state.set(UseResourceState::Pending);
+            retry_attempt.set(0);
+
+            // Clone the options fresh for this run, the same way `fut` is freshly re-derived
+            // from `future` below - `cb` is an `Fn` called on every dependency change and
+            // `restart()`, so nothing captured by it can be moved into the spawned task
+            let options = options.clone();
+            let future = future.clone();
+
+            // Spawn a wrapper task that polls the innner future and watch its dependencies
+            spawn(async move {
+                let mut attempt = 0u32;
+
+                loop {
+                    // Create the user's task
+                    #[allow(clippy::redundant_closure)]
+                    let fut = rc.run_in(|| future());
+
+                    // move the future here, catching any panics so the hook doesn't get stuck
+                    // in `Pending` forever, and pin it so we can poll it
+                    let fut = AssertUnwindSafe(fut).catch_unwind();
+                    pin_mut!(fut);
+
+                    // Run each poll in the context of the reactive scope
+                    // This ensures the scope is properly subscribed to the future's dependencies
+                    let res = future::poll_fn(|cx| rc.run_in(|| fut.poll_unpin(cx))).await;
+
+                    let res = match res {
+                        Ok(res) => res,
+                        Err(_panic) => {
+                            state.set(UseResourceState::Panicked);
+                            break;
+                        }
+                    };
+
+                    // If the caller opted into retries and this looks like a failure, back off
+                    // and run the future again instead of publishing the error right away
+                    if let Some(policy) = &options.retry {
+                        if (policy.is_err)(&res) && attempt < policy.max_attempts {
+                            attempt += 1;
+                            retry_attempt.set(attempt);
+                            sleep(policy.delay_for_attempt(attempt)).await;
+                            continue;
+                        }
+                    }
+
+                    // Set the value and state; `value`'s own reactivity is what wakes up
+                    // `Resource::changes()` consumers, so there's nothing else to notify here
+                    state.set(UseResourceState::Ready);
+                    value.set(Some(res));
+                    break;
+                }
+            })
         })
-    });
+    };
 
     let mut task = use_hook(|| Signal::new(cb()));
 
@@ -81,6 +310,27 @@ where
                 // Wait for the dependencies to change
                 let _ = changed.next().await;
 
+                // If debouncing, wait for a quiet period since the last change before
+                // restarting the task, resetting the timer every time another change comes in
+                if let Some(debounce) = options.debounce {
+                    loop {
+                        let next_change = changed.next();
+                        let timer = sleep(debounce);
+                        pin_mut!(next_change);
+                        pin_mut!(timer);
+
+                        match future::select(next_change, timer).await {
+                            future::Either::Left(_) => continue,
+                            future::Either::Right(_) => break,
+                        }
+                    }
+                }
+
+                // Run any registered cleanup before tearing down the old task
+                if let Some(f) = on_cancel.write().take() {
+                    f();
+                }
+
                 // Stop the old task
                 task.write().cancel();
 
@@ -95,6 +345,8 @@ where
         value,
         state,
         callback: cb,
+        on_cancel,
+        retry_attempt,
     }
 }
 
@@ -104,6 +356,8 @@ pub struct Resource<T: 'static> {
     task: Signal<Task>,
     state: Signal<UseResourceState>,
     callback: UseCallback<Task>,
+    on_cancel: Signal<Option<Box<dyn FnOnce()>>>,
+    retry_attempt: Signal<u32>,
 }
 
 /// A signal that represents the state of a future
@@ -121,6 +375,9 @@ pub enum UseResourceState {
 
     /// The future has completed
     Ready,
+
+    /// The future panicked while running
+    Panicked,
 }
 
 impl<T> Resource<T> {
@@ -129,6 +386,7 @@ impl<T> Resource<T> {
     /// Will not cancel the previous future, but will ignore any values that it
     /// generates.
     pub fn restart(&mut self) {
+        self.fire_on_cancel();
         self.task.write().cancel();
         let new_task = self.callback.call();
         self.task.set(new_task);
@@ -136,10 +394,27 @@ impl<T> Resource<T> {
 
     /// Forcefully cancel a future
     pub fn cancel(&mut self) {
+        self.fire_on_cancel();
         self.state.set(UseResourceState::Stopped);
         self.task.write().cancel();
     }
 
+    /// Register a cleanup callback that runs whenever this resource's task is torn down -
+    /// when it is explicitly [`cancel`](Self::cancel)ed, [`restart`](Self::restart)ed, a
+    /// tracked dependency changes, or the hosting component is unmounted.
+    ///
+    /// Only the most recently registered callback is kept, and it is consumed (cleared)
+    /// the next time it runs.
+    pub fn on_cancel(&mut self, f: impl FnOnce() + 'static) {
+        self.on_cancel.set(Some(Box::new(f)));
+    }
+
+    fn fire_on_cancel(&mut self) {
+        if let Some(f) = self.on_cancel.write().take() {
+            f();
+        }
+    }
+
     /// Pause the future
     pub fn pause(&mut self) {
         self.state.set(UseResourceState::Paused);
@@ -168,10 +443,17 @@ impl<T> Resource<T> {
     pub fn finished(&self) -> bool {
         matches!(
             *self.state.peek(),
-            UseResourceState::Ready | UseResourceState::Stopped
+            UseResourceState::Ready | UseResourceState::Stopped | UseResourceState::Panicked
         )
     }
 
+    /// Has the future panicked?
+    ///
+    /// Reading this does not subscribe to the future's state
+    pub fn panicked(&self) -> bool {
+        matches!(*self.state.peek(), UseResourceState::Panicked)
+    }
+
     /// Get the current state of the future.
     pub fn state(&self) -> ReadOnlySignal<UseResourceState> {
         self.state.into()
@@ -181,6 +463,43 @@ impl<T> Resource<T> {
     pub fn value(&self) -> ReadOnlySignal<Option<T>> {
         self.value.into()
     }
+
+    /// The number of retry attempts made so far for the current run, per
+    /// [`ResourceOptions::retry`]. Resets to `0` on a dependency change or [`Self::restart`].
+    pub fn retry_attempt(&self) -> ReadOnlySignal<u32> {
+        self.retry_attempt.into()
+    }
+
+    /// Get a [`Stream`] of the resolved values of this resource, starting with the current
+    /// value (if any) and yielding every subsequent value the future resolves to.
+    ///
+    /// Uses the same reactive-context-driven tracking as the rest of this hook (instead of a
+    /// platform-specific channel), so it works on every renderer this hook does, including
+    /// the web target. If several values land while the consumer of this stream isn't
+    /// polling, only the most recent one is yielded once it resumes - "watch" semantics,
+    /// not a buffered queue.
+    pub fn changes(&self) -> impl Stream<Item = T>
+    where
+        T: Clone,
+    {
+        let value = self.value;
+        let (rc, changed) = ReactiveContext::new();
+
+        stream::unfold((changed, true), move |(mut changed, mut first)| async move {
+            loop {
+                if !first {
+                    changed.next().await;
+                }
+                first = false;
+
+                // Run the read in the context of the reactive scope so it wakes up again on
+                // the *next* update, the same pattern `use_resource`'s own poll loop uses
+                if let Some(current) = rc.run_in(|| value.read().clone()) {
+                    return Some((current, (changed, false)));
+                }
+            }
+        })
+    }
 }
 
 impl<T> From<Resource<T>> for ReadOnlySignal<Option<T>> {